@@ -3,12 +3,57 @@ use std::fs::File;
 
 use ropey::Rope;
 use crossterm::Result;
+use operational_transform::OperationSeq;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+// Distinguishes an edit that inserted text from one that deleted it
+#[derive(Clone, Copy, PartialEq)]
+enum EditKind {
+    Insert,
+    Delete,
+}
+
+// A single coalesced mutation recorded in the undo/redo history
+// `text` holds the characters that were inserted (or deleted) starting at `index`
+#[derive(Clone)]
+struct Edit {
+    index: usize,
+    text: String,
+    kind: EditKind,
+}
+
+// The class a character belongs to for the purposes of word-wise motion
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum CharClass {
+    Word,
+    Punctuation,
+    Whitespace,
+}
+
+// Classifies a character for word motion purposes
+// In "long word" mode, word and punctuation collapse into a single non-whitespace class
+// so only whitespace separates words
+fn classify(c: char, long: bool) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Whitespace
+    } else if long || c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
 
 #[derive(Default, Clone)]
 // Represents the buffer of the editor
 // Basically a wrapper class for Rope to simplify/extend functionality
 pub struct Buffer {
     rope: Rope,
+    undo_stack: Vec<Edit>,
+    redo_stack: Vec<Edit>,
+    // The edit currently being grown by consecutive same-direction, adjacent-index mutations
+    // (i.e. a "typing run"), not yet pushed onto the undo stack
+    pending_edit: Option<Edit>,
 }
 
 impl Buffer {
@@ -19,6 +64,7 @@ impl Buffer {
 
         Self {
             rope,
+            ..Default::default()
         }
     }
 
@@ -34,15 +80,323 @@ impl Buffer {
     // Inserts a character at the given index
     pub fn insert(&mut self, index: usize, character: char) {
         self.rope.insert_char(index, character);
+        self.record_insert(index, character);
     }
 
     // Deletes a character at the given index
     pub fn delete(&mut self, index: usize) {
+        let character = self.rope.char(index);
         self.rope.remove(index..index + 1);
+        self.record_delete(index, character);
+    }
+
+    // Reverts the most recent edit (or typing run) and returns the buffer index
+    // the cursor should be placed at afterward, or None if there is nothing to undo
+    pub fn undo(&mut self) -> Option<usize> {
+        self.commit_pending();
+
+        let edit = self.undo_stack.pop()?;
+        let cursor_index = self.apply_inverse(&edit);
+        self.redo_stack.push(edit);
+
+        Some(cursor_index)
+    }
+
+    // Re-applies the most recently undone edit and returns the buffer index
+    // the cursor should be placed at afterward, or None if there is nothing to redo
+    pub fn redo(&mut self) -> Option<usize> {
+        let edit = self.redo_stack.pop()?;
+        let cursor_index = self.apply_forward(&edit);
+        self.undo_stack.push(edit);
+
+        Some(cursor_index)
+    }
+
+    // Ends the current typing run, pushing it onto the undo stack
+    // Should be called whenever the cursor moves, a mode switch occurs, or anything else
+    // happens that shouldn't be coalesced into the edit that precedes or follows it
+    pub fn break_edit_run(&mut self) {
+        self.commit_pending();
+    }
+
+    // Records an inserted character, coalescing it into the in-progress typing run when possible
+    fn record_insert(&mut self, index: usize, character: char) {
+        self.redo_stack.clear();
+
+        let joins_run = character != '\n'
+            && matches!(&self.pending_edit, Some(edit) if edit.kind == EditKind::Insert
+                && index == edit.index + edit.text.chars().count());
+
+        if joins_run {
+            self.pending_edit.as_mut().unwrap().text.push(character);
+        } else {
+            self.commit_pending();
+            self.pending_edit = Some(Edit {
+                index,
+                text: character.to_string(),
+                kind: EditKind::Insert,
+            });
+        }
+
+        // A newline always ends its own run so it undoes independently of the typing around it
+        if character == '\n' {
+            self.commit_pending();
+        }
+    }
+
+    // Records a deleted character, coalescing it into the in-progress typing run when possible
+    // Handles both backspace (index decreases each call) and forward-delete (index stays put)
+    fn record_delete(&mut self, index: usize, character: char) {
+        self.redo_stack.clear();
+
+        let joins_run = character != '\n'
+            && matches!(&self.pending_edit, Some(edit) if edit.kind == EditKind::Delete
+                && (index + 1 == edit.index || index == edit.index));
+
+        if joins_run {
+            let pending = self.pending_edit.as_mut().unwrap();
+            if index + 1 == pending.index {
+                pending.text.insert(0, character);
+                pending.index = index;
+            } else {
+                pending.text.push(character);
+            }
+        } else {
+            self.commit_pending();
+            self.pending_edit = Some(Edit {
+                index,
+                text: character.to_string(),
+                kind: EditKind::Delete,
+            });
+        }
+
+        if character == '\n' {
+            self.commit_pending();
+        }
+    }
+
+    // Pushes the in-progress edit (if any) onto the undo stack
+    fn commit_pending(&mut self) {
+        if let Some(edit) = self.pending_edit.take() {
+            self.undo_stack.push(edit);
+        }
+    }
+
+    // Applies an edit to the rope as it originally happened, without recording history
+    fn apply_forward(&mut self, edit: &Edit) -> usize {
+        match edit.kind {
+            EditKind::Insert => {
+                self.rope.insert(edit.index, &edit.text);
+                edit.index + edit.text.chars().count()
+            }
+            EditKind::Delete => {
+                let end = edit.index + edit.text.chars().count();
+                self.rope.remove(edit.index..end);
+                edit.index
+            }
+        }
+    }
+
+    // Applies the inverse of an edit to the rope, without recording history
+    fn apply_inverse(&mut self, edit: &Edit) -> usize {
+        match edit.kind {
+            EditKind::Insert => {
+                let end = edit.index + edit.text.chars().count();
+                self.rope.remove(edit.index..end);
+                edit.index
+            }
+            EditKind::Delete => {
+                self.rope.insert(edit.index, &edit.text);
+                edit.index + edit.text.chars().count()
+            }
+        }
+    }
+
+    // Returns the buffer index of the start of the next word after `index`
+    pub fn next_word_start(&self, index: usize) -> usize {
+        self.next_word_start_impl(index, false)
+    }
+
+    // Same as `next_word_start`, but treats word and punctuation characters as one class
+    pub fn next_long_word_start(&self, index: usize) -> usize {
+        self.next_word_start_impl(index, true)
+    }
+
+    // Returns the buffer index of the start of the word before `index`
+    pub fn prev_word_start(&self, index: usize) -> usize {
+        self.prev_word_start_impl(index, false)
+    }
+
+    // Same as `prev_word_start`, but treats word and punctuation characters as one class
+    pub fn prev_long_word_start(&self, index: usize) -> usize {
+        self.prev_word_start_impl(index, true)
+    }
+
+    // Returns the buffer index of the end of the current or next word after `index`
+    pub fn next_word_end(&self, index: usize) -> usize {
+        self.next_word_end_impl(index, false)
+    }
+
+    // Same as `next_word_end`, but treats word and punctuation characters as one class
+    pub fn next_long_word_end(&self, index: usize) -> usize {
+        self.next_word_end_impl(index, true)
+    }
+
+    fn next_word_start_impl(&self, index: usize, long: bool) -> usize {
+        let len = self.rope.len_chars();
+        if index >= len {
+            return len;
+        }
+
+        let mut chars = self.rope.chars_at(index);
+        let mut i = index;
+        let mut current = chars.next();
+
+        // Advance through the run of chars sharing the starting class (unless it's whitespace)
+        if let Some(c) = current {
+            let start_class = classify(c, long);
+
+            if start_class != CharClass::Whitespace {
+                while let Some(c) = current {
+                    if classify(c, long) != start_class {
+                        break;
+                    }
+                    i += 1;
+                    current = chars.next();
+                }
+            }
+        }
+
+        // Skip any whitespace that follows, landing on the first char of the next word
+        while let Some(c) = current {
+            if classify(c, long) != CharClass::Whitespace {
+                break;
+            }
+            i += 1;
+            current = chars.next();
+        }
+
+        i
+    }
+
+    fn prev_word_start_impl(&self, index: usize, long: bool) -> usize {
+        if index == 0 {
+            return 0;
+        }
+
+        let mut chars = self.rope.chars_at(index);
+        let mut i = index;
+        let mut current = chars.prev();
+
+        // Skip whitespace immediately to the left of the cursor
+        while let Some(c) = current {
+            if classify(c, long) != CharClass::Whitespace {
+                break;
+            }
+            i -= 1;
+            current = chars.prev();
+        }
+
+        // Walk back through the run of chars sharing the class we landed on
+        if let Some(c) = current {
+            let run_class = classify(c, long);
+            while let Some(c) = current {
+                if classify(c, long) != run_class {
+                    break;
+                }
+                i -= 1;
+                current = chars.prev();
+            }
+        }
+
+        i
+    }
+
+    fn next_word_end_impl(&self, index: usize, long: bool) -> usize {
+        let len = self.rope.len_chars();
+        if index + 1 >= len {
+            return index;
+        }
+
+        let mut chars = self.rope.chars_at(index + 1);
+        let mut i = index + 1;
+        let mut current = chars.next();
+
+        // Skip whitespace to find the next run of non-whitespace chars
+        while let Some(c) = current {
+            if classify(c, long) != CharClass::Whitespace {
+                break;
+            }
+            i += 1;
+            current = chars.next();
+        }
+
+        // Advance through the run, stopping on the last char that's still part of it
+        if let Some(c) = current {
+            let run_class = classify(c, long);
+
+            loop {
+                match chars.next() {
+                    Some(next_c) if classify(next_c, long) == run_class => i += 1,
+                    _ => break,
+                }
+            }
+        }
+
+        i
+    }
+
+    // Builds the operation that represents inserting `text` at `index`, relative to the
+    // buffer's size *before* the insertion (for sending to a remote collaborator)
+    pub fn insert_operation(&self, index: usize, text: &str) -> OperationSeq {
+        let mut op = OperationSeq::default();
+        op.retain(index as u64);
+        op.insert(text);
+        op.retain((self.size() - index) as u64);
+
+        op
+    }
+
+    // Builds the operation that represents deleting `len` chars starting at `index`, relative
+    // to the buffer's size *before* the deletion (for sending to a remote collaborator)
+    pub fn delete_operation(&self, index: usize, len: usize) -> OperationSeq {
+        let mut op = OperationSeq::default();
+        op.retain(index as u64);
+        op.delete(len as u64);
+        op.retain((self.size() - index - len) as u64);
+
+        op
+    }
+
+    // Applies an operation (received from a remote collaborator) directly to the buffer,
+    // bypassing undo/redo history since the peer tracks its own
+    pub fn apply_operation(&mut self, op: &OperationSeq) {
+        let text = self.rope.to_string();
+        let result = op
+            .apply(&text)
+            .expect("[INTERNAL ERROR] Failed to apply remote operation");
+
+        self.rope = Rope::from_str(&result);
+
+        // Every stored Edit's `index` is only valid against the document as it stood when it
+        // was recorded. A remote operation can shift text anywhere in the document, so the
+        // local history no longer lines up with it; rather than OT-transforming every stored
+        // range (and the in-progress typing run) against each incoming operation, drop the
+        // history outright once a peer has touched the buffer
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.pending_edit = None;
+    }
+
+    // Converts an absolute buffer index back into a (cursor_x, cursor_y) coordinate
+    pub fn index_to_cursor(&self, index: usize) -> (usize, usize) {
+        let line = self.rope.char_to_line(index);
+        let line_start = self.rope.line_to_char(line);
+
+        (index - line_start, line)
     }
 
     // Returns the starting buffer index of a given line
-    // ! What happens if a line is wrapped to a new line?
     fn line_start_index(&self, line: usize) -> usize {
         let mut index = 0;
 
@@ -59,8 +413,10 @@ impl Buffer {
         )
     }
 
-    // Converts a cursor position to a buffer coordinate
-    // * This will need to be adjusted once scrolling/margins are implemented
+    // Converts a (column, line) cursor position to a buffer coordinate
+    // `cursor_x`/`cursor_y` are always in buffer space; the terminal layer is responsible
+    // for translating screen-space coordinates (accounting for scrolling and the gutter)
+    // into this space before calling it
     pub fn get_buffer_index(&self, (cursor_x, cursor_y): (usize, usize)) -> Option<usize> {
         // Check for out-of-bounds errors for the cursor Y-coordinate
         if cursor_y >= self.line_count() {
@@ -84,17 +440,49 @@ impl Buffer {
         Some(line_start + cursor_x)
     }
 
-    // Returns the length (end X-coordinate) of a line in the buffer
+    // Returns the length (end X-coordinate), in chars, of a line in the buffer
     pub fn line_length(&self, line: usize) -> usize {
-        // TODO: Make this not convert to a String (probably semi-inefficent)
-        let line = self.get_line(line).to_string();
+        let line = self.get_line(line);
+        let len_chars = line.len_chars();
 
         // If the line ends with a newline, don't count it
-        if line.ends_with('\n') {
-            line.len() - 1
+        if len_chars > 0 && line.char(len_chars - 1) == '\n' {
+            len_chars - 1
         } else {
-            line.len()
+            len_chars
+        }
+    }
+
+    // Returns the char offsets of every grapheme cluster boundary in `line`, from 0 up to
+    // (and including) the line's length, so adjacent entries bound one user-perceptible
+    // character and the cursor can move or land cluster-wise rather than char-wise
+    pub fn grapheme_boundaries(&self, line: usize) -> Vec<usize> {
+        let line_length = self.line_length(line);
+        let text = self.line_text(line);
+
+        let mut boundaries = vec![0];
+        let mut char_index = 0;
+
+        for grapheme in text.graphemes(true) {
+            char_index += grapheme.chars().count();
+            if char_index >= line_length {
+                break;
+            }
+            boundaries.push(char_index);
         }
+
+        boundaries.push(line_length);
+        boundaries
+    }
+
+    // Returns the screen column that the char offset `up_to` into `line` maps to, accounting
+    // for wide characters (e.g. CJK) occupying two columns instead of one
+    pub fn display_width(&self, line: usize, up_to: usize) -> usize {
+        self.line_text(line)
+            .chars()
+            .take(up_to)
+            .map(|c| c.width().unwrap_or(0))
+            .sum()
     }
 
     // Get the number of lines in the buffer
@@ -112,9 +500,159 @@ impl Buffer {
         self.rope.lines()
     }
 
+    // Returns the text of a line as an owned String, including its trailing newline if present
+    pub fn line_text(&self, line: usize) -> String {
+        self.get_line(line).to_string()
+    }
+
     // Returns a line from the buffer
     // TODO: Add error handling here, as Rope.line() will panic if the line doesn't exist
     fn get_line(&self, line: usize) -> ropey::RopeSlice {
         self.rope.line(line)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_of(buffer: &Buffer) -> String {
+        buffer.rope.to_string()
+    }
+
+    #[test]
+    fn consecutive_inserts_coalesce_into_one_undo_entry() {
+        let mut buffer = Buffer::default();
+        buffer.insert(0, 'a');
+        buffer.insert(1, 'b');
+        buffer.insert(2, 'c');
+        assert_eq!(text_of(&buffer), "abc");
+
+        buffer.undo();
+        assert_eq!(text_of(&buffer), "");
+    }
+
+    #[test]
+    fn breaking_the_edit_run_splits_insert_history() {
+        let mut buffer = Buffer::default();
+        buffer.insert(0, 'a');
+        buffer.insert(1, 'b');
+        buffer.break_edit_run();
+        buffer.insert(2, 'c');
+
+        buffer.undo();
+        assert_eq!(text_of(&buffer), "ab");
+
+        buffer.undo();
+        assert_eq!(text_of(&buffer), "");
+    }
+
+    #[test]
+    fn undo_then_redo_restores_the_edit() {
+        let mut buffer = Buffer::default();
+        buffer.insert(0, 'a');
+        buffer.insert(1, 'b');
+
+        assert_eq!(buffer.undo(), Some(0));
+        assert_eq!(text_of(&buffer), "");
+
+        assert_eq!(buffer.redo(), Some(2));
+        assert_eq!(text_of(&buffer), "ab");
+    }
+
+    #[test]
+    fn newline_always_ends_its_own_run() {
+        let mut buffer = Buffer::default();
+        buffer.insert(0, 'a');
+        buffer.insert(1, '\n');
+        buffer.insert(2, 'b');
+
+        // "b" undoes on its own, then the newline separately, since it never coalesces
+        // with the insert before or after it
+        buffer.undo();
+        assert_eq!(text_of(&buffer), "a\n");
+
+        buffer.undo();
+        assert_eq!(text_of(&buffer), "a");
+
+        buffer.undo();
+        assert_eq!(text_of(&buffer), "");
+    }
+
+    #[test]
+    fn consecutive_backspaces_coalesce_into_one_undo_entry() {
+        let mut buffer = Buffer::default();
+        buffer.insert(0, 'a');
+        buffer.insert(1, 'b');
+        buffer.insert(2, 'c');
+        buffer.break_edit_run();
+
+        buffer.delete(2);
+        buffer.delete(1);
+        buffer.delete(0);
+        assert_eq!(text_of(&buffer), "");
+
+        buffer.undo();
+        assert_eq!(text_of(&buffer), "abc");
+    }
+
+    fn buffer_with(text: &str) -> Buffer {
+        let mut buffer = Buffer::default();
+        for (i, c) in text.chars().enumerate() {
+            buffer.insert(i, c);
+        }
+        buffer
+    }
+
+    #[test]
+    fn next_word_start_stops_at_the_first_char_of_the_next_word() {
+        let buffer = buffer_with("foo bar");
+        assert_eq!(buffer.next_word_start(0), 4);
+    }
+
+    #[test]
+    fn prev_word_start_stops_at_the_first_char_of_the_current_word() {
+        let buffer = buffer_with("foo bar");
+        assert_eq!(buffer.prev_word_start(7), 4);
+    }
+
+    #[test]
+    fn next_word_end_stops_at_the_last_char_of_the_current_word() {
+        let buffer = buffer_with("foo bar");
+        assert_eq!(buffer.next_word_end(0), 2);
+    }
+
+    #[test]
+    fn next_word_start_treats_punctuation_as_its_own_class() {
+        let buffer = buffer_with("foo-bar baz");
+        assert_eq!(buffer.next_word_start(0), 3);
+    }
+
+    #[test]
+    fn next_long_word_start_treats_punctuation_as_part_of_the_word() {
+        let buffer = buffer_with("foo-bar baz");
+        assert_eq!(buffer.next_long_word_start(0), 8);
+    }
+
+    #[test]
+    fn line_length_counts_chars_not_bytes() {
+        // 'é' is one char but two UTF-8 bytes, so a byte-counting line_length would return 6
+        let buffer = buffer_with("héllo");
+        assert_eq!(buffer.line_length(0), 5);
+    }
+
+    #[test]
+    fn grapheme_boundaries_treat_a_combining_mark_as_one_cluster() {
+        // 'e' followed by a combining acute accent is two chars but one grapheme cluster
+        let buffer = buffer_with("e\u{0301}a");
+        assert_eq!(buffer.grapheme_boundaries(0), vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn display_width_counts_wide_chars_as_two_columns() {
+        // '中' is a double-width CJK character
+        let buffer = buffer_with("中a");
+        assert_eq!(buffer.display_width(0, 1), 2);
+        assert_eq!(buffer.display_width(0, 2), 3);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,203 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use operational_transform::OperationSeq;
+use serde::{Deserialize, Serialize};
+
+// Selects whether this instance listens for a peer or connects out to one, and at what address
+pub enum CollabMode {
+    Host(String),
+    Connect(String),
+}
+
+// A remote collaborator's cursor position, broadcast alongside their edits
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct RemoteCursor {
+    pub buffer_index: usize,
+}
+
+// What's sent over the wire between two `rut` instances
+#[derive(Serialize, Deserialize)]
+enum Message {
+    Operation(OperationSeq),
+    Ack,
+    Cursor(RemoteCursor),
+}
+
+// Something the editor should react to, produced by the background worker
+pub enum RemoteEvent {
+    Operation(OperationSeq),
+    Cursor(RemoteCursor),
+    PeerDisconnected,
+}
+
+// Owns the connection to a collaborator and the background threads that drive it
+pub struct Collaborator {
+    outbound: Sender<Message>,
+    inbound: Receiver<RemoteEvent>,
+    // Kept so Drop can shut the socket down and unblock the reader
+    stream: TcpStream,
+    reader: Option<JoinHandle<()>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl Collaborator {
+    // Starts a collaboration session according to `mode`, blocking until the peer connects
+    pub fn start(mode: CollabMode) -> std::io::Result<Self> {
+        let stream = match mode {
+            CollabMode::Host(addr) => TcpListener::bind(addr)?.accept()?.0,
+            CollabMode::Connect(addr) => TcpStream::connect(addr)?,
+        };
+
+        Ok(Self::spawn(stream))
+    }
+
+    fn spawn(stream: TcpStream) -> Self {
+        let write_stream = stream;
+        let read_stream = write_stream
+            .try_clone()
+            .expect("[INTERNAL ERROR] Failed to clone collaboration socket");
+        let shutdown_stream = write_stream
+            .try_clone()
+            .expect("[INTERNAL ERROR] Failed to clone collaboration socket");
+
+        let (outbound_tx, outbound_rx) = mpsc::channel();
+        let (inbound_tx, inbound_rx) = mpsc::channel();
+
+        // Operations we've sent but that the peer hasn't acknowledged applying yet
+        // Shared because both the writer (which appends on send) and the reader
+        // (which consumes on ack, or transforms against on an incoming operation) touch it
+        let pending: Arc<Mutex<VecDeque<OperationSeq>>> = Arc::new(Mutex::new(VecDeque::new()));
+
+        let writer = thread::spawn({
+            let pending = Arc::clone(&pending);
+            move || write_loop(write_stream, outbound_rx, pending)
+        });
+
+        let reader = thread::spawn({
+            let outbound_tx = outbound_tx.clone();
+            move || read_loop(read_stream, pending, outbound_tx, inbound_tx)
+        });
+
+        Self {
+            outbound: outbound_tx,
+            inbound: inbound_rx,
+            stream: shutdown_stream,
+            reader: Some(reader),
+            writer: Some(writer),
+        }
+    }
+
+    // Queues a local operation to be sent to the peer
+    pub fn send_operation(&self, op: OperationSeq) {
+        let _ = self.outbound.send(Message::Operation(op));
+    }
+
+    // Queues the local cursor position to be broadcast to the peer
+    pub fn send_cursor(&self, cursor: RemoteCursor) {
+        let _ = self.outbound.send(Message::Cursor(cursor));
+    }
+
+    // Drains any events that have arrived from the peer since the last poll
+    pub fn poll(&self) -> Vec<RemoteEvent> {
+        self.inbound.try_iter().collect()
+    }
+}
+
+impl Drop for Collaborator {
+    // Dropping the sender ends the writer's loop; closing the socket ends the reader's
+    fn drop(&mut self) {
+        // Shutting the socket down ends the reader, which drops its clone of the sender;
+        // swapping out our own sender then drops the last one
+        let _ = self.stream.shutdown(Shutdown::Both);
+        drop(std::mem::replace(&mut self.outbound, mpsc::channel().0));
+
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+// Sends every locally queued message to the peer, recording operations in `pending` as they go
+fn write_loop(
+    mut stream: TcpStream,
+    outbound: Receiver<Message>,
+    pending: Arc<Mutex<VecDeque<OperationSeq>>>,
+) {
+    for message in outbound {
+        if let Message::Operation(op) = &message {
+            pending.lock().unwrap().push_back(op.clone());
+        }
+
+        let Ok(mut line) = serde_json::to_string(&message) else {
+            continue;
+        };
+        line.push('\n');
+
+        if stream.write_all(line.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+// Reads messages from the peer, transforming incoming operations against anything still
+// pending acknowledgement before handing them to the editor, and ack-ing whatever it applies
+fn read_loop(
+    stream: TcpStream,
+    pending: Arc<Mutex<VecDeque<OperationSeq>>>,
+    outbound: Sender<Message>,
+    inbound: Sender<RemoteEvent>,
+) {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => {
+                let _ = inbound.send(RemoteEvent::PeerDisconnected);
+                return;
+            }
+            Ok(_) => {}
+        }
+
+        let message = match serde_json::from_str::<Message>(line.trim_end()) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
+
+        match message {
+            Message::Operation(mut op) => {
+                for local_op in pending.lock().unwrap().iter() {
+                    let Ok((_, transformed)) = local_op.transform(&op) else {
+                        continue;
+                    };
+                    op = transformed;
+                }
+
+                if inbound.send(RemoteEvent::Operation(op)).is_err() {
+                    return;
+                }
+                if outbound.send(Message::Ack).is_err() {
+                    return;
+                }
+            }
+            Message::Ack => {
+                pending.lock().unwrap().pop_front();
+            }
+            Message::Cursor(cursor) => {
+                if inbound.send(RemoteEvent::Cursor(cursor)).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -1,12 +1,17 @@
 use std::fs::{File, OpenOptions};
 use std::io::Seek;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use crossterm::Result;
 
 use crate::terminal::{CursorMovement, Terminal, TerminalState};
 use crate::buffer::Buffer;
+use crate::collab::{CollabMode, Collaborator, RemoteCursor, RemoteEvent};
+use crate::highlight::Highlighter;
+use crate::keymap::{Action, Keymap};
 
 // Represents the state of the editor
 // There should only be one instance of this struct at any given point
@@ -14,11 +19,15 @@ pub struct Editor {
     file: Arc<Mutex<File>>,
     buffer: Buffer,
     terminal_state: TerminalState,
+    keymap: Keymap,
+    highlighter: Highlighter,
+    collaborator: Option<Collaborator>,
+    remote_cursor: Option<RemoteCursor>,
 }
 
 impl Editor {
     // Create a new Editor instance
-    pub fn new(filename: &str) -> Self {
+    pub fn new(filename: &str, collab_mode: Option<CollabMode>) -> Self {
         // Open the file
         let file = OpenOptions::new()
             .read(true)
@@ -34,11 +43,23 @@ impl Editor {
         let file = Arc::new(Mutex::new(file));
 
         let terminal_state = TerminalState::new();
+        let keymap = Keymap::load();
+
+        let extension = Path::new(filename).extension().and_then(|ext| ext.to_str());
+        let highlighter = Highlighter::new(extension);
+
+        let collaborator = collab_mode.map(|mode| {
+            Collaborator::start(mode).expect("[INTERNAL ERROR] Failed to start collaboration session")
+        });
 
         Self {
             file,
             buffer,
             terminal_state,
+            keymap,
+            highlighter,
+            collaborator,
+            remote_cursor: None,
         }
     }
 
@@ -47,9 +68,17 @@ impl Editor {
         Terminal {
             state: &mut self.terminal_state,
             buffer: &self.buffer,
+            highlighter: &mut self.highlighter,
+            remote_cursor: self.remote_cursor,
         }
     }
 
+    // Invalidates the cached syntax highlighting from the line containing `buffer_index` onward
+    fn invalidate_highlight_at(&mut self, buffer_index: usize) {
+        let (_, line) = self.buffer.index_to_cursor(buffer_index);
+        self.highlighter.invalidate_from(line);
+    }
+
     // Opens the editor in the terminal and runs the event loop
     pub fn run(&mut self) -> Result<()> {
         // Initialize the terminal
@@ -62,6 +91,16 @@ impl Editor {
     // Enters the event loop for the editor
     fn start_event_loop(&mut self) -> Result<()> {
         loop {
+            // When collaborating, poll for remote activity in between brief waits for local
+            // input instead of blocking on it indefinitely
+            if self.collaborator.is_some() {
+                self.process_remote_events()?;
+
+                if !event::poll(Duration::from_millis(50))? {
+                    continue;
+                }
+            }
+
             // Wait for the next event
             // * This is a blocking call
             let event = event::read()?;
@@ -71,6 +110,49 @@ impl Editor {
         }
     }
 
+    // Applies any operations and cursor updates that have arrived from a remote collaborator
+    fn process_remote_events(&mut self) -> Result<()> {
+        let events = match &self.collaborator {
+            Some(collaborator) => collaborator.poll(),
+            None => return Ok(()),
+        };
+
+        for event in events {
+            match event {
+                RemoteEvent::Operation(op) => {
+                    self.buffer.apply_operation(&op);
+                    // The operation may have touched any line, so the cheapest safe thing to do
+                    // is re-highlight from the top rather than track exactly which lines moved
+                    self.highlighter.invalidate_from(0);
+                    self.terminal().update()?;
+                }
+                RemoteEvent::Cursor(cursor) => {
+                    self.remote_cursor = Some(cursor);
+                    self.terminal().update()?;
+                }
+                RemoteEvent::PeerDisconnected => {
+                    self.collaborator = None;
+                    self.remote_cursor = None;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Sends the local cursor position to the remote collaborator, if one is connected
+    fn broadcast_cursor(&mut self) -> Result<()> {
+        let Some(index) = self.terminal().get_current_buffer_index() else {
+            return Ok(());
+        };
+
+        if let Some(collaborator) = &self.collaborator {
+            collaborator.send_cursor(RemoteCursor { buffer_index: index });
+        }
+
+        Ok(())
+    }
+
     // Handles a generic Event by dispatching it to the appropriate handler function
     fn handle_event(&mut self, event: Event) -> Result<()> {
         match event {
@@ -81,44 +163,58 @@ impl Editor {
         Ok(())
     }
 
-    // Handles a KeyEvent using its code and modifiers
+    // Handles a KeyEvent by resolving it to an Action via the keymap, then dispatching on that
     fn handle_key_event(&mut self, event: KeyEvent) -> Result<()> {
-        use CursorMovement::*;
+        let action = match self.resolve_action(event) {
+            Some(action) => action,
+            None => return Ok(()),
+        };
 
-        match (event.code, event.modifiers) {
-            // Exit the program on Ctrl+C
-            (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
-                self.exit()?;
+        match action {
+            Action::Quit => self.exit()?,
+            Action::Save => self.save()?,
+            Action::Undo => self.undo()?,
+            Action::Redo => self.redo()?,
+            Action::ToggleHighlight => self.toggle_highlight()?,
+            // Moving the cursor always ends the current typing run so a following edit
+            // starts its own undo entry rather than coalescing with the one before the move
+            Action::MoveCursor(movement) => {
+                self.buffer.break_edit_run();
+                self.terminal().move_cursor(movement)?;
             }
-            // Save the file on Ctrl+S
-            (KeyCode::Char('s'), KeyModifiers::CONTROL) => {
-                self.save()?;
+            Action::MoveWordBack => self.move_by_word(Buffer::prev_word_start)?,
+            Action::MoveWordStart => self.move_by_word(Buffer::next_word_start)?,
+            Action::MoveWordEnd => self.move_by_word(Buffer::next_word_end)?,
+            Action::MoveLongWordBack => self.move_by_word(Buffer::prev_long_word_start)?,
+            Action::MoveLongWordStart => self.move_by_word(Buffer::next_long_word_start)?,
+            Action::MoveLongWordEnd => self.move_by_word(Buffer::next_long_word_end)?,
+            Action::DeleteBack => self.remove_char(false)?,
+            Action::DeleteForward => self.remove_char(true)?,
+            Action::InsertNewline => self.insert_char('\n')?,
+            // Only reachable when `event.code` is a Char, since that's the only way resolve_action
+            // produces this action
+            Action::InsertChar => {
+                if let KeyCode::Char(c) = event.code {
+                    self.insert_char(c)?;
+                }
             }
-            // Handle arrow keypresses
-            (KeyCode::Up, KeyModifiers::NONE) => self.terminal().move_cursor(Up)?,
-            (KeyCode::Down, KeyModifiers::NONE) => self.terminal().move_cursor(Down)?,
-            (KeyCode::Left, KeyModifiers::NONE) => self.terminal().move_cursor(Left)?,
-            (KeyCode::Right, KeyModifiers::NONE) => self.terminal().move_cursor(Right)?,
-            // Handle backspace
-            (KeyCode::Backspace, KeyModifiers::NONE) => {
-                self.remove_char(false)?
-            }
-            // Handle delete
-            (KeyCode::Delete, KeyModifiers::NONE) => {
-                self.remove_char(true)?
-            }
-            // Handle enter
-            (KeyCode::Enter, KeyModifiers::NONE) => {
-                self.insert_char('\n')?
-            }
-            // Handle normal characters
-            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
-                self.insert_char(c)?
-            }
-            _ => (),
         }
 
-        Ok(())
+        self.broadcast_cursor()
+    }
+
+    // Resolves a KeyEvent to the Action it should trigger
+    // Falls back to InsertChar for any printable character the keymap doesn't otherwise bind,
+    // since users can't be expected to list every character in their config file
+    fn resolve_action(&self, event: KeyEvent) -> Option<Action> {
+        if let Some(action) = self.keymap.action_for(event.code, event.modifiers) {
+            return Some(action);
+        }
+
+        match (event.code, event.modifiers) {
+            (KeyCode::Char(_), KeyModifiers::NONE | KeyModifiers::SHIFT) => Some(Action::InsertChar),
+            _ => None,
+        }
     }
 
     // [Direct] Inserts a character into the buffer at the cursor position
@@ -130,8 +226,15 @@ impl Editor {
             None => return Ok(()),
         };
 
+        // Let any remote collaborator know about this edit before applying it locally
+        if let Some(collaborator) = &self.collaborator {
+            let op = self.buffer.insert_operation(buffer_coordinate, &character.to_string());
+            collaborator.send_operation(op);
+        }
+
         // Insert the character into the buffer
         self.buffer.insert(buffer_coordinate, character);
+        self.invalidate_highlight_at(buffer_coordinate);
 
         // Perform a frame update
         self.terminal().update()?;
@@ -143,7 +246,7 @@ impl Editor {
         })
     }
 
-    // [Direct] Deletes the character in the buffer immediately preceding the cursor,
+    // [Direct] Deletes the grapheme cluster in the buffer immediately preceding the cursor,
     // or alternatively immediately after the cursor (delete_mode)
     fn remove_char(&mut self, delete_mode: bool) -> Result<()> {
         // Get the buffer coordinate of the cursor
@@ -153,23 +256,108 @@ impl Editor {
             None => return Ok(()),
         };
 
-        // Delete the character in the buffer
-        // The character to delete will either be before the cursor (backspace), or after (delete)
-        self.buffer.delete(match delete_mode {
-            true => buffer_coordinate,
-            false => buffer_coordinate - 1,
-        });
+        // Delete the grapheme cluster in the buffer, so e.g. a base character with a combining
+        // mark is removed as one unit rather than leaving the mark orphaned after a single char
+        // is stripped. At a line boundary there's no cluster to reach across - backspace merges
+        // with the previous line's newline, forward-delete with this line's own, same as before
+        let (cursor_x, cursor_y) = self.buffer.index_to_cursor(buffer_coordinate);
+        let line_start = buffer_coordinate - cursor_x;
+        let line_length = self.buffer.line_length(cursor_y);
+
+        let (delete_index, delete_len) = if delete_mode {
+            if cursor_x == line_length {
+                (buffer_coordinate, 1)
+            } else {
+                let boundaries = self.buffer.grapheme_boundaries(cursor_y);
+                let cluster_end = boundaries
+                    .into_iter()
+                    .find(|&boundary| boundary > cursor_x)
+                    .unwrap_or(line_length);
+                (buffer_coordinate, cluster_end - cursor_x)
+            }
+        } else if cursor_x == 0 {
+            (buffer_coordinate - 1, 1)
+        } else {
+            let boundaries = self.buffer.grapheme_boundaries(cursor_y);
+            let cluster_start = boundaries
+                .into_iter()
+                .rev()
+                .find(|&boundary| boundary < cursor_x)
+                .unwrap_or(0);
+            (line_start + cluster_start, cursor_x - cluster_start)
+        };
+
+        // Let any remote collaborator know about this edit before applying it locally
+        if let Some(collaborator) = &self.collaborator {
+            let op = self.buffer.delete_operation(delete_index, delete_len);
+            collaborator.send_operation(op);
+        }
+
+        // Deleting repeatedly at the same index removes each subsequent char as the rest of
+        // the rope shifts left into it, coalescing into one undo entry the same as a single
+        // forward-delete would (see Buffer::record_delete)
+        for _ in 0..delete_len {
+            self.buffer.delete(delete_index);
+        }
+        self.invalidate_highlight_at(delete_index);
 
         // Perform a frame update
         self.terminal().update()?;
 
-        // Move the cursor left (backspace) or leave it in the same place (delete)
+        // Move the cursor to the start of the removed cluster (backspace), or leave it in
+        // place (delete)
         match delete_mode {
-            false => self.terminal().move_cursor(CursorMovement::Left),
+            false => self.terminal().move_cursor_to_buffer_index(delete_index),
             true => Ok(()),
         }
     }
 
+    // [Direct] Moves the cursor to the buffer index given by a `Buffer` word-motion method
+    fn move_by_word(&mut self, motion: fn(&Buffer, usize) -> usize) -> Result<()> {
+        self.buffer.break_edit_run();
+
+        let current_index = match self.terminal().get_current_buffer_index() {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        let target_index = motion(&self.buffer, current_index);
+
+        self.terminal().move_cursor_to_buffer_index(target_index)
+    }
+
+    // [Direct] Reverts the most recent edit (or typing run) and moves the cursor to it
+    fn undo(&mut self) -> Result<()> {
+        if let Some(index) = self.buffer.undo() {
+            self.invalidate_highlight_at(index);
+            // move_cursor_to_buffer_index repositions the cursor and redraws in one step;
+            // redrawing before it would render the buffer's new content against the
+            // cursor's stale pre-undo position, which can index past the end of a line
+            // the undo just removed
+            self.terminal().move_cursor_to_buffer_index(index)?;
+        }
+
+        Ok(())
+    }
+
+    // [Direct] Re-applies the most recently undone edit and moves the cursor to it
+    fn redo(&mut self) -> Result<()> {
+        if let Some(index) = self.buffer.redo() {
+            self.invalidate_highlight_at(index);
+            self.terminal().move_cursor_to_buffer_index(index)?;
+        }
+
+        Ok(())
+    }
+
+    // [Direct] Toggles syntax highlighting on or off for the current file
+    fn toggle_highlight(&mut self) -> Result<()> {
+        let enabled = self.highlighter.is_enabled();
+        self.highlighter.set_enabled(!enabled);
+
+        self.terminal().update()
+    }
+
     // [Direct] Saves the buffer to the file
     // ! This might crash the program if the file is being saved twice at the same time
     fn save(&mut self) -> Result<()> {
@@ -203,6 +391,10 @@ impl Editor {
         // Close the terminal
         self.terminal().exit()?;
 
+        // process::exit skips Drop impls, so the collaborator's background threads (which
+        // Drop normally joins on) have to be shut down explicitly before it's called
+        self.collaborator.take();
+
         // Exit the program
         std::process::exit(0);
     }
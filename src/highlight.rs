@@ -0,0 +1,142 @@
+use syntect::highlighting::{
+    Highlighter as SyntectHighlighter, HighlightIterator, HighlightState, Style, Theme, ThemeSet,
+};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet};
+
+use crate::buffer::Buffer;
+
+// A single styled run of text within a highlighted line
+pub struct Span {
+    pub style: Style,
+    pub text: String,
+}
+
+// The incremental parse/highlight state resulting from processing one line
+#[derive(Clone)]
+struct LineState {
+    parse_state: ParseState,
+    highlight_state: HighlightState,
+}
+
+impl LineState {
+    fn initial(syntax: &SyntaxReference, highlighter: &SyntectHighlighter) -> Self {
+        Self {
+            parse_state: ParseState::new(syntax),
+            highlight_state: HighlightState::new(highlighter, ScopeStack::new()),
+        }
+    }
+}
+
+// Colorizes buffer lines by language using syntect
+// Caches the state at the end of each line so that editing line N only requires
+// re-highlighting from line N downward, rather than reparsing the whole file
+pub struct Highlighter {
+    syntax_set: SyntaxSet,
+    theme: Theme,
+    syntax: Option<SyntaxReference>,
+    enabled: bool,
+    // line_states[i] holds the state after processing line i; always a contiguous prefix from 0
+    line_states: Vec<LineState>,
+}
+
+impl Highlighter {
+    // Creates a Highlighter for a file with the given extension (e.g. "rs")
+    // Highlighting is disabled automatically if the extension is unknown or absent (plain text)
+    pub fn new(extension: Option<&str>) -> Self {
+        let syntax_set = SyntaxSet::load_defaults_newlines();
+        let theme = ThemeSet::load_defaults().themes["base16-ocean.dark"].clone();
+
+        let syntax = extension
+            .and_then(|ext| syntax_set.find_syntax_by_extension(ext))
+            .cloned();
+        let enabled = syntax.is_some();
+
+        Self {
+            syntax_set,
+            theme,
+            syntax,
+            enabled,
+            line_states: Vec::new(),
+        }
+    }
+
+    // Enables or disables highlighting without forgetting the detected syntax
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled && self.syntax.is_some()
+    }
+
+    // Drops cached state from `line` onward
+    // Call this after any edit that touches `line` or an earlier line
+    pub fn invalidate_from(&mut self, line: usize) {
+        self.line_states.truncate(line);
+    }
+
+    // Returns the styled spans for a line, extending the cache up through the line itself
+    pub fn highlight_line(&mut self, buffer: &Buffer, line: usize) -> Vec<Span> {
+        let text = buffer.line_text(line);
+
+        let Some(syntax) = self.syntax.clone() else {
+            return vec![Span { style: Style::default(), text }];
+        };
+
+        if !self.is_enabled() {
+            return vec![Span { style: Style::default(), text }];
+        }
+
+        self.fill_cache_up_to(&syntax, buffer, line);
+
+        let highlighter = SyntectHighlighter::new(&self.theme);
+
+        let mut state = if line == 0 {
+            LineState::initial(&syntax, &highlighter)
+        } else {
+            self.line_states[line - 1].clone()
+        };
+
+        let ops = state
+            .parse_state
+            .parse_line(&text, &self.syntax_set)
+            .expect("[INTERNAL ERROR] Failed to parse line for syntax highlighting");
+
+        let spans = HighlightIterator::new(&mut state.highlight_state, &ops, &text, &highlighter)
+            .map(|(style, text)| Span { style, text: text.to_string() })
+            .collect();
+
+        if self.line_states.len() == line {
+            self.line_states.push(state);
+        }
+
+        spans
+    }
+
+    // Fills the cache (if needed) with the end-of-line state for every line before `line`
+    fn fill_cache_up_to(&mut self, syntax: &SyntaxReference, buffer: &Buffer, line: usize) {
+        let highlighter = SyntectHighlighter::new(&self.theme);
+
+        while self.line_states.len() < line {
+            let index = self.line_states.len();
+            let text = buffer.line_text(index);
+
+            let mut state = if index == 0 {
+                LineState::initial(syntax, &highlighter)
+            } else {
+                self.line_states[index - 1].clone()
+            };
+
+            let ops = state
+                .parse_state
+                .parse_line(&text, &self.syntax_set)
+                .expect("[INTERNAL ERROR] Failed to parse line for syntax highlighting");
+
+            // Only the resulting state is needed here; the spans themselves are discarded
+            HighlightIterator::new(&mut state.highlight_state, &ops, &text, &highlighter)
+                .for_each(drop);
+
+            self.line_states.push(state);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::terminal::CursorMovement;
+
+// An editing action that can be bound to a key combination
+// `InsertChar` is never present in the keymap itself; it's the fallback for any
+// unbound printable character (see Editor::resolve_action)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    InsertChar,
+    InsertNewline,
+    DeleteBack,
+    DeleteForward,
+    MoveCursor(CursorMovement),
+    MoveWordBack,
+    MoveWordStart,
+    MoveWordEnd,
+    MoveLongWordBack,
+    MoveLongWordStart,
+    MoveLongWordEnd,
+    Undo,
+    Redo,
+    Save,
+    Quit,
+    ToggleHighlight,
+}
+
+// The actions a user's config file can refer to by name, alongside that name
+const NAMED_ACTIONS: &[(&str, Action)] = &[
+    ("insert_newline", Action::InsertNewline),
+    ("delete_back", Action::DeleteBack),
+    ("delete_forward", Action::DeleteForward),
+    ("move_char_up", Action::MoveCursor(CursorMovement::Up)),
+    ("move_char_down", Action::MoveCursor(CursorMovement::Down)),
+    ("move_char_left", Action::MoveCursor(CursorMovement::Left)),
+    ("move_char_right", Action::MoveCursor(CursorMovement::Right)),
+    ("move_word_left", Action::MoveWordBack),
+    ("move_word_right", Action::MoveWordStart),
+    ("move_word_end", Action::MoveWordEnd),
+    ("move_long_word_left", Action::MoveLongWordBack),
+    ("move_long_word_right", Action::MoveLongWordStart),
+    ("move_long_word_end", Action::MoveLongWordEnd),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("save", Action::Save),
+    ("quit", Action::Quit),
+    ("toggle_highlight", Action::ToggleHighlight),
+];
+
+// Maps key combinations to the action they should trigger
+pub struct Keymap {
+    bindings: HashMap<(KeyCode, KeyModifiers), Action>,
+}
+
+impl Keymap {
+    // Builds the keymap: the default bindings, overridden/extended by the user's config file
+    pub fn load() -> Self {
+        let mut bindings = default_bindings();
+
+        if let Some(overrides) = read_config() {
+            for (name, key_spec) in overrides {
+                let Some(&(_, action)) = NAMED_ACTIONS.iter().find(|(n, _)| *n == name) else {
+                    continue;
+                };
+
+                if let Some(key) = parse_key(&key_spec) {
+                    bindings.insert(key, action);
+                }
+            }
+        }
+
+        Self { bindings }
+    }
+
+    // Looks up the action bound to a key combination, if any
+    pub fn action_for(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+        self.bindings.get(&(code, modifiers)).copied()
+    }
+}
+
+// The default bindings: the editor's original quit/save/arrow/backspace/delete/enter keys,
+// plus undo/redo, word motions and the highlighting toggle
+fn default_bindings() -> HashMap<(KeyCode, KeyModifiers), Action> {
+    use KeyModifiers as Mod;
+
+    HashMap::from([
+        ((KeyCode::Char('c'), Mod::CONTROL), Action::Quit),
+        ((KeyCode::Char('s'), Mod::CONTROL), Action::Save),
+        ((KeyCode::Char('z'), Mod::CONTROL), Action::Undo),
+        ((KeyCode::Char('y'), Mod::CONTROL), Action::Redo),
+        ((KeyCode::Up, Mod::NONE), Action::MoveCursor(CursorMovement::Up)),
+        ((KeyCode::Down, Mod::NONE), Action::MoveCursor(CursorMovement::Down)),
+        ((KeyCode::Left, Mod::NONE), Action::MoveCursor(CursorMovement::Left)),
+        ((KeyCode::Right, Mod::NONE), Action::MoveCursor(CursorMovement::Right)),
+        ((KeyCode::Left, Mod::CONTROL), Action::MoveWordBack),
+        ((KeyCode::Right, Mod::CONTROL), Action::MoveWordStart),
+        ((KeyCode::Right, Mod::CONTROL | Mod::SHIFT), Action::MoveWordEnd),
+        ((KeyCode::Left, Mod::ALT), Action::MoveLongWordBack),
+        ((KeyCode::Right, Mod::ALT), Action::MoveLongWordStart),
+        ((KeyCode::Right, Mod::ALT | Mod::SHIFT), Action::MoveLongWordEnd),
+        ((KeyCode::Backspace, Mod::NONE), Action::DeleteBack),
+        ((KeyCode::Delete, Mod::NONE), Action::DeleteForward),
+        ((KeyCode::Enter, Mod::NONE), Action::InsertNewline),
+        ((KeyCode::Char('h'), Mod::ALT), Action::ToggleHighlight),
+    ])
+}
+
+// Reads the user's keymap overrides from `<config dir>/rut/keymap.toml`, if present
+fn read_config() -> Option<HashMap<String, String>> {
+    let path = dirs::config_dir()?.join("rut").join("keymap.toml");
+    let contents = fs::read_to_string(path).ok()?;
+
+    toml::from_str(&contents).ok()
+}
+
+// Parses a key specification like "Ctrl+Shift+Right" or "z" into a (KeyCode, KeyModifiers) pair
+fn parse_key(spec: &str) -> Option<(KeyCode, KeyModifiers)> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut parts = spec.split('+').peekable();
+
+    while let Some(part) = parts.next() {
+        // The last part of the spec is the key itself; everything before it is a modifier
+        if parts.peek().is_none() {
+            let code = match part {
+                "Up" => KeyCode::Up,
+                "Down" => KeyCode::Down,
+                "Left" => KeyCode::Left,
+                "Right" => KeyCode::Right,
+                "Enter" => KeyCode::Enter,
+                "Backspace" => KeyCode::Backspace,
+                "Delete" => KeyCode::Delete,
+                "Tab" => KeyCode::Tab,
+                "Esc" => KeyCode::Esc,
+                _ => {
+                    let mut chars = part.chars();
+                    let c = chars.next()?;
+
+                    // Reject multi-char specs that aren't one of the named keys above
+                    if chars.next().is_some() {
+                        return None;
+                    }
+
+                    KeyCode::Char(c)
+                }
+            };
+
+            return Some((code, modifiers));
+        }
+
+        match part {
+            "Ctrl" => modifiers |= KeyModifiers::CONTROL,
+            "Shift" => modifiers |= KeyModifiers::SHIFT,
+            "Alt" => modifiers |= KeyModifiers::ALT,
+            _ => return None,
+        }
+    }
+
+    None
+}
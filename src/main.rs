@@ -1,25 +1,43 @@
 mod buffer;
+mod collab;
 mod editor;
+mod highlight;
+mod keymap;
 mod terminal;
 
 use crossterm::Result;
 
-use buffer::Buffer;
-use buffer::DeletionMode;
+use collab::CollabMode;
 use editor::Editor;
-use terminal::Terminal;
 
 fn main() -> Result<()> {
-    // Make sure the user has provided one argument (filename to open)
-    if std::env::args().len() != 2 {
-        println!("Usage: rut <filename>");
+    let args: Vec<String> = std::env::args().collect();
+
+    // Make sure the user has provided at least one argument (filename to open)
+    if args.len() < 2 {
+        println!("Usage: rut <filename> [--host <addr> | --connect <addr>]");
         std::process::exit(1);
     }
 
-    // Get the filename from the command line
-    let filename = std::env::args().nth(1).unwrap();
+    let filename = &args[1];
+    let collab_mode = parse_collab_mode(&args[2..]);
 
     // Create and run the editor
-    let mut editor = Editor::new(&filename);
+    let mut editor = Editor::new(filename, collab_mode);
     editor.run()
 }
+
+// Parses the optional `--host <addr>` / `--connect <addr>` flags that enable collaboration
+fn parse_collab_mode(args: &[String]) -> Option<CollabMode> {
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--host" => return Some(CollabMode::Host(args.next()?.clone())),
+            "--connect" => return Some(CollabMode::Connect(args.next()?.clone())),
+            _ => continue,
+        }
+    }
+
+    None
+}
@@ -0,0 +1,234 @@
+use std::io::{stdout, Stdout, Write};
+
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::{cursor, queue, style, terminal};
+use crossterm::Result;
+
+use crate::buffer::Buffer;
+use crate::collab::RemoteCursor;
+use crate::highlight::Highlighter;
+
+// Represents a cardinal direction to move the cursor in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CursorMovement {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+// Holds the state of the terminal that needs to persist across frames
+// Kept separate from Terminal so it can live on Editor without borrowing the buffer
+pub struct TerminalState {
+    stdout: Stdout,
+    cursor_x: usize,
+    cursor_y: usize,
+    // The buffer line currently drawn at the top of the viewport
+    scroll_top: usize,
+}
+
+impl TerminalState {
+    // Create a new TerminalState with the cursor at the origin
+    pub fn new() -> Self {
+        Self {
+            stdout: stdout(),
+            cursor_x: 0,
+            cursor_y: 0,
+            scroll_top: 0,
+        }
+    }
+}
+
+// Returns the width of the line-number gutter for a buffer with the given number of lines
+fn gutter_width(line_count: usize) -> usize {
+    (line_count.max(1) as f64).log10().floor() as usize + 1
+}
+
+// A short-lived handle combining the persistent TerminalState with a reference to the current Buffer
+// Constructed on demand via Editor::terminal() so rendering always sees up-to-date buffer contents
+pub struct Terminal<'a> {
+    pub state: &'a mut TerminalState,
+    pub buffer: &'a Buffer,
+    pub highlighter: &'a mut Highlighter,
+    // The collaborator's last known cursor position, if one is connected
+    pub remote_cursor: Option<RemoteCursor>,
+}
+
+impl<'a> Terminal<'a> {
+    // Enters raw mode and draws the initial frame
+    pub fn init(&mut self) -> Result<()> {
+        terminal::enable_raw_mode()?;
+        queue!(self.state.stdout, terminal::EnterAlternateScreen)?;
+
+        self.update()
+    }
+
+    // Leaves raw mode and restores the screen
+    pub fn exit(&mut self) -> Result<()> {
+        queue!(self.state.stdout, terminal::LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()
+    }
+
+    // Redraws the visible slice of the buffer (with its line-number gutter) and repositions the cursor
+    pub fn update(&mut self) -> Result<()> {
+        let (_, rows) = terminal::size()?;
+        let rows = rows as usize;
+        let gutter_width = gutter_width(self.buffer.line_count());
+
+        queue!(self.state.stdout, Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+
+        let visible_line_count = (self.buffer.line_count() - self.state.scroll_top).min(rows);
+        let first_visible_line = self.state.scroll_top;
+        let last_visible_line = first_visible_line + visible_line_count;
+
+        for line in first_visible_line..last_visible_line {
+            queue!(
+                self.state.stdout,
+                style::Print(format!("{:>width$} ", line + 1, width = gutter_width))
+            )?;
+
+            for span in self.highlighter.highlight_line(self.buffer, line) {
+                // `span.text` carries the line's own trailing '\n' (syntect needs it present
+                // to parse correctly), but in raw mode a bare '\n' advances the cursor down
+                // without returning it to column 0 - printing it would double-advance on top
+                // of the explicit `MoveToNextLine` below, so it's stripped here instead
+                let text = span.text.trim_end_matches('\n');
+                queue!(
+                    self.state.stdout,
+                    style::SetForegroundColor(style::Color::Rgb {
+                        r: span.style.foreground.r,
+                        g: span.style.foreground.g,
+                        b: span.style.foreground.b,
+                    }),
+                    style::Print(text)
+                )?;
+            }
+
+            queue!(self.state.stdout, style::ResetColor, cursor::MoveToNextLine(1))?;
+        }
+
+        // Draw the remote collaborator's cursor, if any, as a reverse-video marker over
+        // whatever character already occupies that cell
+        // The cached position is only refreshed when a Cursor message arrives, so a local
+        // edit (or one of the peer's own, not yet followed by a new Cursor message) can
+        // leave it pointing past the end of the buffer; skip it until a fresh position
+        // arrives rather than indexing out of bounds
+        let remote_cursor = self
+            .remote_cursor
+            .filter(|cursor| cursor.buffer_index <= self.buffer.size());
+
+        if let Some(remote_cursor) = remote_cursor {
+            let (remote_x, remote_y) = self.buffer.index_to_cursor(remote_cursor.buffer_index);
+
+            if remote_y >= first_visible_line && remote_y < last_visible_line {
+                let marker = self
+                    .buffer
+                    .line_text(remote_y)
+                    .chars()
+                    .nth(remote_x)
+                    .unwrap_or(' ');
+
+                queue!(
+                    self.state.stdout,
+                    cursor::MoveTo(
+                        (gutter_width + 1 + self.buffer.display_width(remote_y, remote_x)) as u16,
+                        (remote_y - self.state.scroll_top) as u16
+                    ),
+                    style::SetAttribute(style::Attribute::Reverse),
+                    style::Print(marker),
+                    style::SetAttribute(style::Attribute::Reset)
+                )?;
+            }
+        }
+
+        // Screen column 0 of the text area is buffer column 0, so the cursor is offset
+        // by the gutter width (plus its trailing space) and the scrolled-past lines
+        let cursor_column = self.buffer.display_width(self.state.cursor_y, self.state.cursor_x);
+        queue!(
+            self.state.stdout,
+            cursor::MoveTo(
+                (gutter_width + 1 + cursor_column) as u16,
+                (self.state.cursor_y - self.state.scroll_top) as u16
+            )
+        )?;
+
+        self.state.stdout.flush()
+    }
+
+    // Moves the cursor one grapheme cluster in the given direction, clamping to the buffer's bounds
+    pub fn move_cursor(&mut self, movement: CursorMovement) -> Result<()> {
+        match movement {
+            CursorMovement::Up => self.state.cursor_y = self.state.cursor_y.saturating_sub(1),
+            CursorMovement::Down => {
+                if self.state.cursor_y + 1 < self.buffer.line_count() {
+                    self.state.cursor_y += 1;
+                }
+            }
+            CursorMovement::Left => {
+                let boundaries = self.buffer.grapheme_boundaries(self.state.cursor_y);
+                self.state.cursor_x = boundaries
+                    .iter()
+                    .rev()
+                    .find(|&&boundary| boundary < self.state.cursor_x)
+                    .copied()
+                    .unwrap_or(0);
+            }
+            CursorMovement::Right => {
+                let boundaries = self.buffer.grapheme_boundaries(self.state.cursor_y);
+                self.state.cursor_x = boundaries
+                    .iter()
+                    .find(|&&boundary| boundary > self.state.cursor_x)
+                    .copied()
+                    .unwrap_or(self.state.cursor_x);
+            }
+        }
+
+        // Clamp the X-coordinate to the length of the line the cursor landed on, then snap it
+        // back to the nearest grapheme boundary so a vertical move never leaves it mid-cluster
+        let line_length = self.buffer.line_length(self.state.cursor_y);
+        if self.state.cursor_x > line_length {
+            self.state.cursor_x = line_length;
+        }
+        self.state.cursor_x = self
+            .buffer
+            .grapheme_boundaries(self.state.cursor_y)
+            .into_iter()
+            .rfind(|&boundary| boundary <= self.state.cursor_x)
+            .unwrap_or(0);
+
+        self.scroll_to_cursor()?;
+        self.update()
+    }
+
+    // Moves the cursor directly to the given buffer index
+    pub fn move_cursor_to_buffer_index(&mut self, index: usize) -> Result<()> {
+        let (cursor_x, cursor_y) = self.buffer.index_to_cursor(index);
+
+        self.state.cursor_x = cursor_x;
+        self.state.cursor_y = cursor_y;
+
+        self.scroll_to_cursor()?;
+        self.update()
+    }
+
+    // Scrolls the viewport whenever the cursor has moved past its top or bottom edge, just far
+    // enough to bring it back into view (one line for a single-line move)
+    fn scroll_to_cursor(&mut self) -> Result<()> {
+        let (_, rows) = terminal::size()?;
+        let rows = rows as usize;
+
+        if self.state.cursor_y < self.state.scroll_top {
+            self.state.scroll_top = self.state.cursor_y;
+        } else if self.state.cursor_y >= self.state.scroll_top + rows {
+            self.state.scroll_top = self.state.cursor_y + 1 - rows;
+        }
+
+        Ok(())
+    }
+
+    // Returns the buffer index of the current cursor position
+    pub fn get_current_buffer_index(&self) -> Option<usize> {
+        self.buffer
+            .get_buffer_index((self.state.cursor_x, self.state.cursor_y))
+    }
+}